@@ -0,0 +1,249 @@
+//! Buffer backend.
+
+use std::fmt;
+use std::ops::Range;
+
+/// Buffer errors.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BufferError {
+  /// Overflow when setting a value with a specific index.
+  ///
+  /// Contains the index and the size of the buffer.
+  Overflow { index: usize, buffer_len: usize },
+  /// Too few values were passed to fill a buffer.
+  ///
+  /// Contains the number of passed values and the size of the buffer.
+  TooFewValues {
+    provided_len: usize,
+    buffer_len: usize,
+  },
+  /// Too many values were passed to fill a buffer.
+  ///
+  /// Contains the number of passed values and the size of the buffer.
+  TooManyValues {
+    provided_len: usize,
+    buffer_len: usize,
+  },
+  /// Mapping a buffer failed.
+  MapFailed,
+}
+
+impl fmt::Display for BufferError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      BufferError::Overflow { index, buffer_len } => write!(
+        f,
+        "buffer overflow (index = {}, size = {})",
+        index, buffer_len
+      ),
+
+      BufferError::TooFewValues {
+        provided_len,
+        buffer_len,
+      } => write!(
+        f,
+        "too few values passed to the buffer (nb = {}, size = {})",
+        provided_len, buffer_len
+      ),
+
+      BufferError::TooManyValues {
+        provided_len,
+        buffer_len,
+      } => write!(
+        f,
+        "too many values passed to the buffer (nb = {}, size = {})",
+        provided_len, buffer_len
+      ),
+
+      BufferError::MapFailed => write!(f, "buffer mapping failed"),
+    }
+  }
+}
+
+/// Usage hint given to the GPU driver about how a buffer's content will be accessed.
+///
+/// This mirrors the OpenGL usage hints passed to `glBufferData`: the first part of the name
+/// tells how often the content is expected to change, the second part tells who reads from it
+/// and who writes to it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BufferMode {
+  /// The content is uploaded once by the application and read from at draw time.
+  ///
+  /// This is the mode of choice for data that never changes, such as static vertex or index
+  /// buffers.
+  StaticDraw,
+  /// The content is uploaded once and read back by the application.
+  StaticRead,
+  /// The content is uploaded once and used as the source of a GPU-to-GPU copy.
+  StaticCopy,
+  /// The content is expected to change repeatedly and is read from at draw time.
+  ///
+  /// This is the mode of choice for per-frame streams, such as dynamic vertex data or uniform
+  /// buffers.
+  StreamDraw,
+  /// The content is expected to change repeatedly and read back by the application.
+  StreamRead,
+  /// The content is expected to change repeatedly and used as the source of a GPU-to-GPU copy.
+  StreamCopy,
+  /// The content is changed occasionally and read from at draw time.
+  DynamicDraw,
+  /// The content is changed occasionally and read back by the application.
+  DynamicRead,
+  /// The content is changed occasionally and used as the source of a GPU-to-GPU copy.
+  DynamicCopy,
+}
+
+/// Access hints for a mutable range mapping, passed down to `glMapBufferRange`.
+///
+/// Both flags assume the caller knows the mapped region is not currently read by any in-flight
+/// draw; setting them when that is not the case will produce visible corruption.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MapRangeAccess {
+  /// Maps to `GL_MAP_INVALIDATE_RANGE_BIT`: the previous content of the range can be discarded.
+  pub invalidate: bool,
+  /// Maps to `GL_MAP_UNSYNCHRONIZED_BIT`: skip the implicit sync the driver would otherwise do
+  /// to make sure the GPU is done reading the range.
+  pub unsynchronized: bool,
+}
+
+impl Default for BufferMode {
+  /// Defaults to [`BufferMode::StreamDraw`], which was the implicit behavior before this enum
+  /// was introduced.
+  fn default() -> Self {
+    BufferMode::StreamDraw
+  }
+}
+
+pub unsafe trait BufferBase {
+  type BufferRepr;
+}
+
+pub unsafe trait Buffer<T>: BufferBase {
+  unsafe fn new_buffer(&mut self, len: usize, mode: BufferMode) -> Result<Self::BufferRepr, BufferError>;
+
+  /// Allocate a buffer that is persistently mapped for the whole of its lifetime, so that
+  /// [`Buffer::set`] / [`Buffer::write_whole`] write straight to CPU-visible memory instead of
+  /// mapping and unmapping on every call.
+  ///
+  /// Backends that cannot provide a persistent mapping (or whose driver lacks the required
+  /// extension) may fall back to [`Buffer::new_buffer`]; the non-persistent API keeps working
+  /// either way.
+  unsafe fn new_persistent_buffer(
+    &mut self,
+    len: usize,
+    mode: BufferMode,
+  ) -> Result<Self::BufferRepr, BufferError> {
+    self.new_buffer(len, mode)
+  }
+
+  unsafe fn destroy_buffer(buffer: &mut Self::BufferRepr);
+
+  unsafe fn len(buffer: &Self::BufferRepr) -> usize;
+
+  unsafe fn from_slice<S>(
+    &mut self,
+    slice: S,
+    mode: BufferMode,
+  ) -> Result<Self::BufferRepr, BufferError>
+  where
+    S: AsRef<[T]>;
+
+  unsafe fn repeat(
+    &mut self,
+    len: usize,
+    value: T,
+    mode: BufferMode,
+  ) -> Result<Self::BufferRepr, BufferError>
+  where
+    T: Copy;
+
+  unsafe fn at(buffer: &Self::BufferRepr, i: usize) -> Option<T>
+  where
+    T: Copy;
+
+  unsafe fn whole(buffer: &Self::BufferRepr) -> Vec<T>
+  where
+    T: Copy;
+
+  unsafe fn set(buffer: &mut Self::BufferRepr, i: usize, x: T) -> Result<(), BufferError>
+  where
+    T: Copy;
+
+  unsafe fn write_whole(buffer: &mut Self::BufferRepr, values: &[T]) -> Result<(), BufferError>;
+
+  unsafe fn clear(buffer: &mut Self::BufferRepr, x: T) -> Result<(), BufferError>
+  where
+    T: Copy;
+
+  /// Fence off the buffer's current content so a future write waits for the GPU to be done
+  /// consuming it.
+  ///
+  /// This is the synchronization primitive, not the call site: the render/tessellation code that
+  /// submits a draw reading from a persistently-mapped buffer is responsible for calling this
+  /// right after that draw, the same way it is responsible for binding the buffer in the first
+  /// place. It is a no-op for buffers that are not persistently mapped, so calling it
+  /// unconditionally after any draw is always safe.
+  unsafe fn fence_buffer(_buffer: &mut Self::BufferRepr) {}
+
+  /// Copy `len` items from `src` (starting at `src_offset`) to `dst` (starting at `dst_offset`)
+  /// entirely on the GPU, without any CPU round trip.
+  ///
+  /// Returns [`BufferError::Overflow`] if either range falls outside of its buffer.
+  ///
+  /// No default body: a backend that silently no-op'd this would leave `dst` untouched while
+  /// callers believe the copy happened, which is worse than a compile error forcing every
+  /// implementor to provide a real (or explicitly-unsupported) implementation.
+  unsafe fn copy_buffer(
+    src: &Self::BufferRepr,
+    dst: &mut Self::BufferRepr,
+    src_offset: usize,
+    dst_offset: usize,
+    len: usize,
+  ) -> Result<(), BufferError>;
+
+  /// Orphan the buffer's storage, telling the driver the previous content can be discarded.
+  ///
+  /// Useful right before overwriting a streaming buffer so the driver can hand back a fresh
+  /// allocation instead of stalling on the GPU still reading the old one. Defaults to a no-op,
+  /// since skipping the orphan only costs a stall rather than corrupting data, so backends that
+  /// cannot cheaply support it are not forced to implement it.
+  unsafe fn invalidate_buffer(_buffer: &mut Self::BufferRepr) -> Result<(), BufferError> {
+    Ok(())
+  }
+}
+
+pub unsafe trait BufferSlice<T>: BufferBase {
+  type SliceRepr;
+  type SliceMutRepr;
+
+  unsafe fn slice_buffer(buffer: &Self::BufferRepr) -> Result<Self::SliceRepr, BufferError>;
+
+  unsafe fn slice_buffer_mut(buffer: &mut Self::BufferRepr) -> Result<Self::SliceMutRepr, BufferError>;
+
+  /// Map a sub-region of `buffer`, expressed as an index range, for reading.
+  ///
+  /// Returns [`BufferError::Overflow`] if `range` is not fully contained in the buffer.
+  unsafe fn slice_buffer_range(
+    buffer: &Self::BufferRepr,
+    range: Range<usize>,
+  ) -> Result<Self::SliceRepr, BufferError>;
+
+  /// Map a sub-region of `buffer`, expressed as an index range, for writing.
+  ///
+  /// `access` lets the caller opt into `GL_MAP_INVALIDATE_RANGE_BIT` / `GL_MAP_UNSYNCHRONIZED_BIT`
+  /// when it knows the region is not read by any pending draw. Returns [`BufferError::Overflow`]
+  /// if `range` is not fully contained in the buffer.
+  unsafe fn slice_buffer_range_mut(
+    buffer: &mut Self::BufferRepr,
+    range: Range<usize>,
+    access: MapRangeAccess,
+  ) -> Result<Self::SliceMutRepr, BufferError>;
+
+  unsafe fn destroy_buffer_slice(slice: &mut Self::SliceRepr);
+
+  unsafe fn destroy_buffer_slice_mut(slice: &mut Self::SliceMutRepr);
+
+  unsafe fn obtain_slice(slice: &Self::SliceRepr) -> Result<&[T], BufferError>;
+
+  unsafe fn obtain_slice_mut(slice: &mut Self::SliceMutRepr) -> Result<&mut [T], BufferError>;
+}
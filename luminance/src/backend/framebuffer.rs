@@ -21,6 +21,14 @@ pub enum FramebufferError {
   ///
   /// This happens when finalizing the construction of the framebuffer.
   Incomplete(IncompleteReason),
+  /// A depth or stencil blit was requested with a filter other than [`BlitFilter::Nearest`].
+  ///
+  /// `glBlitFramebuffer` only accepts nearest filtering for depth / stencil data.
+  InvalidBlitFilter,
+  /// `glBlitFramebuffer` reported an error (e.g. incompatible formats or sample counts).
+  ///
+  /// Contains the raw GL error code returned by `glGetError` right after the blit.
+  BlitFailed(u32),
 }
 
 impl fmt::Display for FramebufferError {
@@ -29,6 +37,13 @@ impl fmt::Display for FramebufferError {
       FramebufferError::TextureError(ref e) => write!(f, "framebuffer texture error: {}", e),
 
       FramebufferError::Incomplete(ref e) => write!(f, "incomplete framebuffer: {}", e),
+
+      FramebufferError::InvalidBlitFilter => write!(
+        f,
+        "depth / stencil blits only support nearest filtering"
+      ),
+
+      FramebufferError::BlitFailed(code) => write!(f, "framebuffer blit failed (GL error {})", code),
     }
   }
 }
@@ -81,6 +96,32 @@ impl fmt::Display for IncompleteReason {
   }
 }
 
+/// A pixel rectangle, as consumed by `glBlitFramebuffer` (`(x0, y0)` to `(x1, y1)`, exclusive).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlitRect {
+  pub x0: u32,
+  pub y0: u32,
+  pub x1: u32,
+  pub y1: u32,
+}
+
+/// Which buffers a blit copies.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BlitMask {
+  pub color: bool,
+  pub depth: bool,
+  pub stencil: bool,
+}
+
+/// Filter used to resample a blit when the source and destination rectangles differ in size.
+///
+/// Only [`BlitFilter::Nearest`] is valid for depth / stencil blits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlitFilter {
+  Nearest,
+  Linear,
+}
+
 pub unsafe trait Framebuffer<L, D>
 where
   Self: TextureBase<L, D>,
@@ -110,4 +151,20 @@ where
   ) -> Result<(), FramebufferError>;
 
   unsafe fn framebuffer_size(framebuffer: &Self::FramebufferRepr) -> D::Size;
+
+  /// Copy a region of `src` into a region of `dst`, as `glBlitFramebuffer` does, binding `src`
+  /// to `GL_READ_FRAMEBUFFER` and `dst` to `GL_DRAW_FRAMEBUFFER`.
+  ///
+  /// The primary use is resolving a multisampled framebuffer into a single-sample one, but it
+  /// also covers cheap downscale / upscale copies and depth-buffer sharing. Returns
+  /// [`FramebufferError::InvalidBlitFilter`] if `filter` is [`BlitFilter::Linear`] while `mask`
+  /// selects depth or stencil.
+  unsafe fn blit_framebuffer(
+    src: &Self::FramebufferRepr,
+    dst: &Self::FramebufferRepr,
+    src_rect: BlitRect,
+    dst_rect: BlitRect,
+    mask: BlitMask,
+    filter: BlitFilter,
+  ) -> Result<(), FramebufferError>;
 }
@@ -4,33 +4,125 @@ use gl;
 use gl::types::*;
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::ffi::CStr;
 use std::mem;
+use std::ops::Range;
 use std::os::raw::c_void;
 use std::ptr;
 use std::rc::Rc;
 use std::slice;
 
-use crate::backend::buffer::{Buffer, BufferBase, BufferError, BufferSlice as BufferSliceBackend};
+use crate::backend::buffer::{
+  Buffer, BufferBase, BufferError, BufferMode, BufferSlice as BufferSliceBackend, MapRangeAccess,
+};
 use crate::backend::gl::state::{Bind, GLState};
 use crate::backend::gl::GL;
 
+/// Translate a [`BufferMode`] into the `GLenum` usage hint expected by `glBufferData`.
+fn gl_buffer_mode(mode: BufferMode) -> GLenum {
+  match mode {
+    BufferMode::StaticDraw => gl::STATIC_DRAW,
+    BufferMode::StaticRead => gl::STATIC_READ,
+    BufferMode::StaticCopy => gl::STATIC_COPY,
+    BufferMode::StreamDraw => gl::STREAM_DRAW,
+    BufferMode::StreamRead => gl::STREAM_READ,
+    BufferMode::StreamCopy => gl::STREAM_COPY,
+    BufferMode::DynamicDraw => gl::DYNAMIC_DRAW,
+    BufferMode::DynamicRead => gl::DYNAMIC_READ,
+    BufferMode::DynamicCopy => gl::DYNAMIC_COPY,
+  }
+}
+
+/// State specific to a persistently-mapped [`RawBuffer`].
+///
+/// `ptr` stays valid for the buffer's whole lifetime; `fence` guards the last region the GPU was
+/// told to consume, so a write can wait on it before touching that memory again.
+#[derive(Clone, Copy)]
+struct PersistentMapping {
+  ptr: *mut c_void,
+  fence: Option<GLsync>,
+}
+
 /// OpenGL buffer.
 #[derive(Clone)]
 pub struct RawBuffer {
   pub(crate) handle: GLuint,
   pub(crate) bytes: usize,
   pub(crate) len: usize,
+  /// Usage hint this buffer was allocated with, so it can be re-specified identically later on
+  /// (e.g. when orphaning the storage).
+  pub(crate) usage: GLenum,
+  /// Set when this buffer was allocated with `glBufferStorage` and kept mapped for its whole
+  /// lifetime.
+  persistent: Option<PersistentMapping>,
   state: Rc<RefCell<GLState>>,
 }
 
+/// Whether the current context advertises `name` in its `GL_EXTENSIONS` list.
+///
+/// Used as a fallback for core-profile drivers that expose a feature as an ARB extension
+/// without bumping the core version that introduced it.
+unsafe fn has_gl_extension(name: &str) -> bool {
+  let mut count = 0;
+  gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+
+  for i in 0..count {
+    let ext = gl::GetStringi(gl::EXTENSIONS, i as GLuint);
+    if ext.is_null() {
+      continue;
+    }
+
+    if CStr::from_ptr(ext as *const _).to_bytes() == name.as_bytes() {
+      return true;
+    }
+  }
+
+  false
+}
+
+/// Whether the driver exposes GL 4.4 (and hence `glBufferStorage` / `GL_MAP_PERSISTENT_BIT`),
+/// either via the core version or the `GL_ARB_buffer_storage` extension.
+unsafe fn supports_persistent_mapping() -> bool {
+  let mut major = 0;
+  let mut minor = 0;
+  gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+  gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+
+  (major, minor) >= (4, 4) || has_gl_extension("GL_ARB_buffer_storage")
+}
+
+/// Whether the driver exposes GL 4.3 (and hence `glInvalidateBufferData`), either via the core
+/// version or the `GL_ARB_invalidate_subdata` extension.
+unsafe fn supports_invalidate_subdata() -> bool {
+  let mut major = 0;
+  let mut minor = 0;
+  gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+  gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+
+  (major, minor) >= (4, 3) || has_gl_extension("GL_ARB_invalidate_subdata")
+}
+
+/// Wait for (and consume) the fence guarding a persistent mapping, if any is set.
+unsafe fn wait_persistent_fence(mapping: &mut PersistentMapping) {
+  if let Some(fence) = mapping.fence.take() {
+    gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, gl::TIMEOUT_IGNORED);
+    gl::DeleteSync(fence);
+  }
+}
+
 unsafe impl BufferBase for GL {
   type BufferRepr = RawBuffer;
 }
 
 unsafe impl<T> Buffer<T> for GL {
-  unsafe fn new_buffer(&mut self, len: usize) -> Result<Self::BufferRepr, BufferError> {
+  unsafe fn new_buffer(
+    &mut self,
+    len: usize,
+    mode: BufferMode,
+  ) -> Result<Self::BufferRepr, BufferError> {
     let mut buffer: GLuint = 0;
     let bytes = mem::size_of::<T>() * len;
+    let usage = gl_buffer_mode(mode);
 
     // generate a buffer and force binding the handle; this prevent side-effects from previous bound
     // resources to prevent binding the buffer
@@ -40,31 +132,95 @@ unsafe impl<T> Buffer<T> for GL {
       .borrow_mut()
       .bind_array_buffer(buffer, Bind::Forced);
 
-    gl::BufferData(
-      gl::ARRAY_BUFFER,
-      bytes as isize,
-      ptr::null(),
-      gl::STREAM_DRAW,
-    );
+    gl::BufferData(gl::ARRAY_BUFFER, bytes as isize, ptr::null(), usage);
+
+    Ok(RawBuffer {
+      handle: buffer,
+      bytes,
+      len,
+      usage,
+      persistent: None,
+      state: self.state.clone(),
+    })
+  }
+
+  unsafe fn new_persistent_buffer(
+    &mut self,
+    len: usize,
+    mode: BufferMode,
+  ) -> Result<Self::BufferRepr, BufferError> {
+    if !supports_persistent_mapping() {
+      // no glBufferStorage / GL_MAP_PERSISTENT_BIT on this driver; fall back to the regular
+      // map-on-demand buffer instead
+      return <Self as Buffer<T>>::new_buffer(self, len, mode);
+    }
+
+    let mut buffer: GLuint = 0;
+    let bytes = mem::size_of::<T>() * len;
+    let usage = gl_buffer_mode(mode);
+    let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+
+    gl::GenBuffers(1, &mut buffer);
+    self
+      .state
+      .borrow_mut()
+      .bind_array_buffer(buffer, Bind::Forced);
+
+    gl::BufferStorage(gl::ARRAY_BUFFER, bytes as isize, ptr::null(), flags);
+    let ptr = gl::MapBufferRange(gl::ARRAY_BUFFER, 0, bytes as isize, flags);
+
+    if ptr.is_null() {
+      self.state.borrow_mut().unbind_buffer(buffer);
+      gl::DeleteBuffers(1, &buffer);
+      return Err(BufferError::MapFailed);
+    }
 
     Ok(RawBuffer {
       handle: buffer,
       bytes,
       len,
+      usage,
+      persistent: Some(PersistentMapping { ptr, fence: None }),
       state: self.state.clone(),
     })
   }
 
   unsafe fn destroy_buffer(buffer: &mut Self::BufferRepr) {
+    if let Some(mut mapping) = buffer.persistent.take() {
+      if let Some(fence) = mapping.fence.take() {
+        gl::DeleteSync(fence);
+      }
+
+      buffer
+        .state
+        .borrow_mut()
+        .bind_array_buffer(buffer.handle, Bind::Cached);
+      gl::UnmapBuffer(gl::ARRAY_BUFFER);
+    }
+
     buffer.state.borrow_mut().unbind_buffer(buffer.handle);
     gl::DeleteBuffers(1, &buffer.handle);
   }
 
+  unsafe fn fence_buffer(buffer: &mut Self::BufferRepr) {
+    if let Some(mapping) = buffer.persistent.as_mut() {
+      if let Some(old_fence) = mapping.fence.take() {
+        gl::DeleteSync(old_fence);
+      }
+
+      mapping.fence = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+    }
+  }
+
   unsafe fn len(buffer: &Self::BufferRepr) -> usize {
     buffer.len
   }
 
-  unsafe fn from_slice<S>(&mut self, slice: S) -> Result<Self::BufferRepr, BufferError>
+  unsafe fn from_slice<S>(
+    &mut self,
+    slice: S,
+    mode: BufferMode,
+  ) -> Result<Self::BufferRepr, BufferError>
   where
     S: AsRef<[T]>,
   {
@@ -72,6 +228,7 @@ unsafe impl<T> Buffer<T> for GL {
     let slice = slice.as_ref();
     let len = slice.len();
     let bytes = mem::size_of::<T>() * len;
+    let usage = gl_buffer_mode(mode);
 
     gl::GenBuffers(1, &mut buffer);
     self
@@ -82,23 +239,29 @@ unsafe impl<T> Buffer<T> for GL {
       gl::ARRAY_BUFFER,
       bytes as isize,
       slice.as_ptr() as *const c_void,
-      gl::STREAM_DRAW,
+      usage,
     );
 
     Ok(RawBuffer {
       handle: buffer,
       bytes,
       len,
+      usage,
+      persistent: None,
       state: self.state.clone(),
     })
   }
 
-  unsafe fn repeat(&mut self, len: usize, value: T) -> Result<Self::BufferRepr, BufferError>
+  unsafe fn repeat(
+    &mut self,
+    len: usize,
+    value: T,
+    mode: BufferMode,
+  ) -> Result<Self::BufferRepr, BufferError>
   where
     T: Copy,
   {
-    //let mut buf = self.new_buffer(len)?;
-    let mut buf = <Self as Buffer<T>>::new_buffer(self, len)?;
+    let mut buf = <Self as Buffer<T>>::new_buffer(self, len, mode)?;
     Self::clear(&mut buf, value)?;
     Ok(buf)
   }
@@ -109,6 +272,9 @@ unsafe impl<T> Buffer<T> for GL {
   {
     if i >= buffer.len {
       None
+    } else if let Some(mapping) = buffer.persistent.as_ref() {
+      let ptr = mapping.ptr as *const T;
+      Some(*ptr.add(i))
     } else {
       buffer
         .state
@@ -126,6 +292,11 @@ unsafe impl<T> Buffer<T> for GL {
   where
     T: Copy,
   {
+    if let Some(mapping) = buffer.persistent.as_ref() {
+      let ptr = mapping.ptr as *const T;
+      return slice::from_raw_parts(ptr, buffer.len).to_vec();
+    }
+
     buffer
       .state
       .borrow_mut()
@@ -146,6 +317,12 @@ unsafe impl<T> Buffer<T> for GL {
         index: i,
         buffer_len: buffer.len,
       })
+    } else if let Some(mapping) = buffer.persistent.as_mut() {
+      wait_persistent_fence(mapping);
+      let ptr = mapping.ptr as *mut T;
+      *ptr.add(i) = x;
+
+      Ok(())
     } else {
       buffer
         .state
@@ -182,6 +359,12 @@ unsafe impl<T> Buffer<T> for GL {
       _ => in_bytes,
     };
 
+    if let Some(mapping) = buffer.persistent.as_mut() {
+      wait_persistent_fence(mapping);
+      ptr::copy_nonoverlapping(values.as_ptr() as *const c_void, mapping.ptr, real_bytes);
+      return Ok(());
+    }
+
     buffer
       .state
       .borrow_mut()
@@ -199,16 +382,99 @@ unsafe impl<T> Buffer<T> for GL {
   {
     Self::write_whole(buffer, &vec![x; buffer.len])
   }
+
+  unsafe fn copy_buffer(
+    src: &Self::BufferRepr,
+    dst: &mut Self::BufferRepr,
+    src_offset: usize,
+    dst_offset: usize,
+    len: usize,
+  ) -> Result<(), BufferError> {
+    let copy_bytes = len * mem::size_of::<T>();
+    let src_offset_bytes = src_offset * mem::size_of::<T>();
+    let dst_offset_bytes = dst_offset * mem::size_of::<T>();
+
+    if src_offset_bytes + copy_bytes > src.bytes {
+      return Err(BufferError::Overflow {
+        index: src_offset + len,
+        buffer_len: src.len,
+      });
+    }
+
+    if dst_offset_bytes + copy_bytes > dst.bytes {
+      return Err(BufferError::Overflow {
+        index: dst_offset + len,
+        buffer_len: dst.len,
+      });
+    }
+
+    gl::BindBuffer(gl::COPY_READ_BUFFER, src.handle);
+    gl::BindBuffer(gl::COPY_WRITE_BUFFER, dst.handle);
+    gl::CopyBufferSubData(
+      gl::COPY_READ_BUFFER,
+      gl::COPY_WRITE_BUFFER,
+      src_offset_bytes as isize,
+      dst_offset_bytes as isize,
+      copy_bytes as isize,
+    );
+
+    Ok(())
+  }
+
+  unsafe fn invalidate_buffer(buffer: &mut Self::BufferRepr) -> Result<(), BufferError> {
+    if supports_invalidate_subdata() {
+      gl::InvalidateBufferData(buffer.handle);
+      return Ok(());
+    }
+
+    // the storage of a persistently-mapped buffer is immutable; without
+    // glInvalidateBufferData there is no way to orphan it, so just keep it as-is
+    if buffer.persistent.is_some() {
+      return Ok(());
+    }
+
+    buffer
+      .state
+      .borrow_mut()
+      .bind_array_buffer(buffer.handle, Bind::Cached);
+    gl::BufferData(
+      gl::ARRAY_BUFFER,
+      buffer.bytes as isize,
+      ptr::null(),
+      buffer.usage,
+    );
+
+    Ok(())
+  }
 }
 
 pub struct BufferSlice<T> {
   buffer: RawBuffer,
   ptr: *const T,
+  len: usize,
 }
 
 pub struct BufferSliceMut<T> {
   buffer: RawBuffer,
   ptr: *mut T,
+  len: usize,
+}
+
+/// Validate that `range` fits inside a buffer of `buffer_len` elements and turn it into
+/// `(offset_bytes, len_bytes, len_elements)`.
+fn range_to_bytes<T>(buffer_len: usize, range: &Range<usize>) -> Result<(isize, isize, usize), BufferError> {
+  if range.start > range.end || range.end > buffer_len {
+    return Err(BufferError::Overflow {
+      index: range.end,
+      buffer_len,
+    });
+  }
+
+  let len = range.end - range.start;
+  let offset_bytes = (range.start * mem::size_of::<T>()) as isize;
+  let len_bytes = (len * mem::size_of::<T>()) as isize;
+
+  Ok((offset_bytes, len_bytes, len))
 }
 
 unsafe impl<T> BufferSliceBackend<T> for GL {
@@ -217,40 +483,101 @@ unsafe impl<T> BufferSliceBackend<T> for GL {
   type SliceMutRepr = BufferSliceMut<T>;
 
   unsafe fn slice_buffer(buffer: &Self::BufferRepr) -> Result<Self::SliceRepr, BufferError> {
+    Self::slice_buffer_range(buffer, 0..buffer.len)
+  }
+
+  unsafe fn slice_buffer_mut(
+    buffer: &mut Self::BufferRepr,
+  ) -> Result<Self::SliceMutRepr, BufferError> {
+    let len = buffer.len;
+    Self::slice_buffer_range_mut(buffer, 0..len, MapRangeAccess::default())
+  }
+
+  unsafe fn slice_buffer_range(
+    buffer: &Self::BufferRepr,
+    range: Range<usize>,
+  ) -> Result<Self::SliceRepr, BufferError> {
+    let (offset_bytes, len_bytes, len) = range_to_bytes::<T>(buffer.len, &range)?;
+
+    if let Some(mapping) = buffer.persistent.as_ref() {
+      // already mapped for the buffer's whole lifetime; just offset into it instead of calling
+      // glMapBufferRange again (the storage wasn't allocated with MAP_READ_BIT, so that call
+      // would fail anyway)
+      let ptr = (mapping.ptr as *const u8).add(offset_bytes as usize) as *const T;
+      let buffer = buffer.clone();
+
+      return Ok(BufferSlice { buffer, ptr, len });
+    }
+
     buffer
       .state
       .borrow_mut()
       .bind_array_buffer(buffer.handle, Bind::Cached);
 
-    let ptr = gl::MapBuffer(gl::ARRAY_BUFFER, gl::READ_ONLY) as *const T;
+    let ptr =
+      gl::MapBufferRange(gl::ARRAY_BUFFER, offset_bytes, len_bytes, gl::MAP_READ_BIT) as *const T;
     let buffer = buffer.clone();
 
     if ptr.is_null() {
       Err(BufferError::MapFailed)
     } else {
-      Ok(BufferSlice { buffer, ptr })
+      Ok(BufferSlice { buffer, ptr, len })
     }
   }
 
-  unsafe fn slice_buffer_mut(
+  unsafe fn slice_buffer_range_mut(
     buffer: &mut Self::BufferRepr,
+    range: Range<usize>,
+    access: MapRangeAccess,
   ) -> Result<Self::SliceMutRepr, BufferError> {
+    let (offset_bytes, len_bytes, len) = range_to_bytes::<T>(buffer.len, &range)?;
+
+    if let Some(mapping) = buffer.persistent.as_mut() {
+      // wait for the GPU to be done with whatever this range last fenced, then hand out a
+      // pointer straight into the persistent mapping instead of mapping it a second time
+      wait_persistent_fence(mapping);
+      let ptr = (mapping.ptr as *mut u8).add(offset_bytes as usize) as *mut T;
+      let buffer = buffer.clone();
+
+      return Ok(BufferSliceMut { buffer, ptr, len });
+    }
+
+    let mut flags = gl::MAP_WRITE_BIT;
+    if access.invalidate {
+      flags |= gl::MAP_INVALIDATE_RANGE_BIT;
+    }
+    if access.unsynchronized {
+      flags |= gl::MAP_UNSYNCHRONIZED_BIT;
+    }
+    // MAP_READ_BIT cannot be combined with MAP_INVALIDATE_RANGE_BIT / MAP_UNSYNCHRONIZED_BIT, so
+    // only request it in the plain case; that plain case is also what slice_buffer_mut uses, and
+    // it must stay readable to match the pre-range-mapping glMapBuffer(..., READ_WRITE) behavior
+    if !access.invalidate && !access.unsynchronized {
+      flags |= gl::MAP_READ_BIT;
+    }
+
     buffer
       .state
       .borrow_mut()
       .bind_array_buffer(buffer.handle, Bind::Cached);
 
-    let ptr = gl::MapBuffer(gl::ARRAY_BUFFER, gl::READ_WRITE) as *mut T;
+    let ptr = gl::MapBufferRange(gl::ARRAY_BUFFER, offset_bytes, len_bytes, flags) as *mut T;
     let buffer = buffer.clone();
 
     if ptr.is_null() {
       Err(BufferError::MapFailed)
     } else {
-      Ok(BufferSliceMut { buffer, ptr })
+      Ok(BufferSliceMut { buffer, ptr, len })
     }
   }
 
   unsafe fn destroy_buffer_slice(slice: &mut Self::SliceRepr) {
+    // a persistently-mapped buffer stays mapped for its whole lifetime; only unmap the ones we
+    // mapped just for this slice
+    if slice.buffer.persistent.is_some() {
+      return;
+    }
+
     slice
       .buffer
       .state
@@ -260,6 +587,10 @@ unsafe impl<T> BufferSliceBackend<T> for GL {
   }
 
   unsafe fn destroy_buffer_slice_mut(slice: &mut Self::SliceMutRepr) {
+    if slice.buffer.persistent.is_some() {
+      return;
+    }
+
     slice
       .buffer
       .state
@@ -269,10 +600,10 @@ unsafe impl<T> BufferSliceBackend<T> for GL {
   }
 
   unsafe fn obtain_slice(slice: &Self::SliceRepr) -> Result<&[T], BufferError> {
-    Ok(slice::from_raw_parts(slice.ptr, slice.buffer.len))
+    Ok(slice::from_raw_parts(slice.ptr, slice.len))
   }
 
   unsafe fn obtain_slice_mut(slice: &mut Self::SliceMutRepr) -> Result<&mut [T], BufferError> {
-    Ok(slice::from_raw_parts_mut(slice.ptr, slice.buffer.len))
+    Ok(slice::from_raw_parts_mut(slice.ptr, slice.len))
   }
 }
@@ -0,0 +1,135 @@
+//! OpenGL framebuffer implementation.
+
+use gl;
+use gl::types::*;
+
+use crate::backend::framebuffer::{
+  BlitFilter, BlitMask, BlitRect, Framebuffer, FramebufferError,
+};
+use crate::backend::gl::texture::RawTexture;
+use crate::backend::gl::GL;
+use crate::backend::texture::{Dimensionable, Layerable, Sampler, TextureBase};
+
+/// OpenGL framebuffer object.
+pub struct RawFramebuffer<D>
+where
+  D: Dimensionable,
+{
+  pub(crate) handle: GLuint,
+  size: D::Size,
+}
+
+fn gl_blit_mask(mask: BlitMask) -> GLbitfield {
+  let mut gl_mask: GLbitfield = 0;
+
+  if mask.color {
+    gl_mask |= gl::COLOR_BUFFER_BIT;
+  }
+  if mask.depth {
+    gl_mask |= gl::DEPTH_BUFFER_BIT;
+  }
+  if mask.stencil {
+    gl_mask |= gl::STENCIL_BUFFER_BIT;
+  }
+
+  gl_mask
+}
+
+fn gl_blit_filter(filter: BlitFilter) -> GLenum {
+  match filter {
+    BlitFilter::Nearest => gl::NEAREST,
+    BlitFilter::Linear => gl::LINEAR,
+  }
+}
+
+unsafe impl<L, D> Framebuffer<L, D> for GL
+where
+  L: Layerable,
+  D: Dimensionable,
+  D::Size: Clone,
+  Self: TextureBase<L, D, TextureRepr = RawTexture>,
+{
+  type FramebufferRepr = RawFramebuffer<D>;
+
+  unsafe fn new_framebuffer(
+    &mut self,
+    size: D::Size,
+    _mipmaps: usize,
+    _sampler: &Sampler,
+  ) -> Result<Self::FramebufferRepr, FramebufferError> {
+    let mut handle: GLuint = 0;
+    gl::GenFramebuffers(1, &mut handle);
+    gl::BindFramebuffer(gl::FRAMEBUFFER, handle);
+
+    Ok(RawFramebuffer { handle, size })
+  }
+
+  unsafe fn destroy_framebuffer(framebuffer: &mut Self::FramebufferRepr) {
+    gl::DeleteFramebuffers(1, &framebuffer.handle);
+  }
+
+  unsafe fn attach_color_texture(
+    framebuffer: &mut Self::FramebufferRepr,
+    texture: &Self::TextureRepr,
+    attachment_index: usize,
+  ) -> Result<(), FramebufferError> {
+    gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.handle);
+    gl::FramebufferTexture(
+      gl::FRAMEBUFFER,
+      gl::COLOR_ATTACHMENT0 + attachment_index as GLenum,
+      texture.handle,
+      0,
+    );
+
+    Ok(())
+  }
+
+  unsafe fn attach_depth_texture(
+    framebuffer: &mut Self::FramebufferRepr,
+    texture: &Self::TextureRepr,
+  ) -> Result<(), FramebufferError> {
+    gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.handle);
+    gl::FramebufferTexture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture.handle, 0);
+
+    Ok(())
+  }
+
+  unsafe fn framebuffer_size(framebuffer: &Self::FramebufferRepr) -> D::Size {
+    framebuffer.size.clone()
+  }
+
+  unsafe fn blit_framebuffer(
+    src: &Self::FramebufferRepr,
+    dst: &Self::FramebufferRepr,
+    src_rect: BlitRect,
+    dst_rect: BlitRect,
+    mask: BlitMask,
+    filter: BlitFilter,
+  ) -> Result<(), FramebufferError> {
+    if filter == BlitFilter::Linear && (mask.depth || mask.stencil) {
+      return Err(FramebufferError::InvalidBlitFilter);
+    }
+
+    gl::BindFramebuffer(gl::READ_FRAMEBUFFER, src.handle);
+    gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, dst.handle);
+    gl::BlitFramebuffer(
+      src_rect.x0 as GLint,
+      src_rect.y0 as GLint,
+      src_rect.x1 as GLint,
+      src_rect.y1 as GLint,
+      dst_rect.x0 as GLint,
+      dst_rect.y0 as GLint,
+      dst_rect.x1 as GLint,
+      dst_rect.y1 as GLint,
+      gl_blit_mask(mask),
+      gl_blit_filter(filter),
+    );
+
+    let err = gl::GetError();
+    if err != gl::NO_ERROR {
+      return Err(FramebufferError::BlitFailed(err));
+    }
+
+    Ok(())
+  }
+}
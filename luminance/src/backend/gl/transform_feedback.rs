@@ -0,0 +1,135 @@
+//! OpenGL transform feedback implementation.
+
+use gl;
+use gl::types::*;
+
+use crate::backend::buffer::BufferBase;
+use crate::backend::gl::GL;
+use crate::backend::transform_feedback::{
+  TransformFeedback, TransformFeedbackBase, TransformFeedbackError, TransformFeedbackMode,
+};
+
+fn gl_transform_feedback_mode(mode: TransformFeedbackMode) -> GLenum {
+  match mode {
+    TransformFeedbackMode::Points => gl::POINTS,
+    TransformFeedbackMode::Lines => gl::LINES,
+    TransformFeedbackMode::Triangles => gl::TRIANGLES,
+  }
+}
+
+/// A transform feedback session.
+///
+/// Holds the query object used to count captured primitives and remembers whether it turned
+/// `GL_RASTERIZER_DISCARD` on, so it can be turned back off symmetrically.
+pub struct GlTransformFeedback {
+  query: GLuint,
+  max_bindings: GLuint,
+  rasterizer_discard: bool,
+}
+
+unsafe impl TransformFeedbackBase for GL {
+  type TransformFeedbackRepr = GlTransformFeedback;
+}
+
+unsafe impl TransformFeedback for GL {
+  unsafe fn new_transform_feedback(
+    &mut self,
+  ) -> Result<Self::TransformFeedbackRepr, TransformFeedbackError> {
+    let mut query: GLuint = 0;
+    gl::GenQueries(1, &mut query);
+
+    let mut max_bindings: GLint = 0;
+    gl::GetIntegerv(gl::MAX_TRANSFORM_FEEDBACK_BUFFERS, &mut max_bindings);
+
+    Ok(GlTransformFeedback {
+      query,
+      max_bindings: max_bindings as GLuint,
+      rasterizer_discard: false,
+    })
+  }
+
+  unsafe fn destroy_transform_feedback(tf: &mut Self::TransformFeedbackRepr) {
+    gl::DeleteQueries(1, &tf.query);
+  }
+
+  unsafe fn bind_transform_feedback_buffer(
+    tf: &mut Self::TransformFeedbackRepr,
+    index: u32,
+    buffer: &Self::BufferRepr,
+  ) -> Result<(), TransformFeedbackError> {
+    if index >= tf.max_bindings {
+      return Err(TransformFeedbackError::TooManyBindings {
+        index,
+        max: tf.max_bindings,
+      });
+    }
+
+    gl::BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, index, buffer.handle);
+
+    Ok(())
+  }
+
+  unsafe fn bind_transform_feedback_buffer_range(
+    tf: &mut Self::TransformFeedbackRepr,
+    index: u32,
+    buffer: &Self::BufferRepr,
+    offset_bytes: usize,
+    size_bytes: usize,
+  ) -> Result<(), TransformFeedbackError> {
+    if index >= tf.max_bindings {
+      return Err(TransformFeedbackError::TooManyBindings {
+        index,
+        max: tf.max_bindings,
+      });
+    }
+
+    if offset_bytes + size_bytes > buffer.bytes {
+      return Err(TransformFeedbackError::RangeOutOfBounds {
+        offset_bytes,
+        size_bytes,
+        buffer_bytes: buffer.bytes,
+      });
+    }
+
+    gl::BindBufferRange(
+      gl::TRANSFORM_FEEDBACK_BUFFER,
+      index,
+      buffer.handle,
+      offset_bytes as isize,
+      size_bytes as isize,
+    );
+
+    Ok(())
+  }
+
+  unsafe fn begin_transform_feedback(
+    tf: &mut Self::TransformFeedbackRepr,
+    mode: TransformFeedbackMode,
+    discard_rasterizer: bool,
+  ) {
+    if discard_rasterizer {
+      gl::Enable(gl::RASTERIZER_DISCARD);
+    }
+
+    tf.rasterizer_discard = discard_rasterizer;
+
+    gl::BeginQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN, tf.query);
+    gl::BeginTransformFeedback(gl_transform_feedback_mode(mode));
+  }
+
+  unsafe fn end_transform_feedback(tf: &mut Self::TransformFeedbackRepr) {
+    gl::EndTransformFeedback();
+    gl::EndQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN);
+
+    if tf.rasterizer_discard {
+      gl::Disable(gl::RASTERIZER_DISCARD);
+      tf.rasterizer_discard = false;
+    }
+  }
+
+  unsafe fn primitives_written(tf: &Self::TransformFeedbackRepr) -> u32 {
+    let mut count: GLuint = 0;
+    gl::GetQueryObjectuiv(tf.query, gl::QUERY_RESULT, &mut count);
+    count
+  }
+}
@@ -0,0 +1,96 @@
+//! Transform feedback backend.
+
+use std::fmt;
+
+use crate::backend::buffer::BufferBase;
+
+/// Transform feedback errors.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TransformFeedbackError {
+  /// A capture buffer was bound to a binding point the driver does not support.
+  ///
+  /// Contains the requested index and the driver's `GL_MAX_TRANSFORM_FEEDBACK_BUFFERS`.
+  TooManyBindings { index: u32, max: u32 },
+  /// The requested byte range does not fit inside the capture buffer.
+  RangeOutOfBounds {
+    offset_bytes: usize,
+    size_bytes: usize,
+    buffer_bytes: usize,
+  },
+}
+
+impl fmt::Display for TransformFeedbackError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      TransformFeedbackError::TooManyBindings { index, max } => write!(
+        f,
+        "transform feedback binding index {} exceeds the {} buffers supported by the driver",
+        index, max
+      ),
+
+      TransformFeedbackError::RangeOutOfBounds {
+        offset_bytes,
+        size_bytes,
+        buffer_bytes,
+      } => write!(
+        f,
+        "transform feedback range [{}, {}) does not fit in a buffer of {} bytes",
+        offset_bytes,
+        offset_bytes + size_bytes,
+        buffer_bytes
+      ),
+    }
+  }
+}
+
+/// Primitive mode a transform-feedback session captures.
+///
+/// `glBeginTransformFeedback` only accepts these three.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransformFeedbackMode {
+  Points,
+  Lines,
+  Triangles,
+}
+
+pub unsafe trait TransformFeedbackBase {
+  type TransformFeedbackRepr;
+}
+
+pub unsafe trait TransformFeedback: TransformFeedbackBase + BufferBase {
+  unsafe fn new_transform_feedback(
+    &mut self,
+  ) -> Result<Self::TransformFeedbackRepr, TransformFeedbackError>;
+
+  unsafe fn destroy_transform_feedback(tf: &mut Self::TransformFeedbackRepr);
+
+  /// Bind a whole capture buffer to an indexed `GL_TRANSFORM_FEEDBACK_BUFFER` binding point.
+  unsafe fn bind_transform_feedback_buffer(
+    tf: &mut Self::TransformFeedbackRepr,
+    index: u32,
+    buffer: &Self::BufferRepr,
+  ) -> Result<(), TransformFeedbackError>;
+
+  /// Bind a byte range of a capture buffer to an indexed `GL_TRANSFORM_FEEDBACK_BUFFER` binding
+  /// point.
+  unsafe fn bind_transform_feedback_buffer_range(
+    tf: &mut Self::TransformFeedbackRepr,
+    index: u32,
+    buffer: &Self::BufferRepr,
+    offset_bytes: usize,
+    size_bytes: usize,
+  ) -> Result<(), TransformFeedbackError>;
+
+  /// Start capturing, optionally discarding rasterization for a capture-only pass.
+  unsafe fn begin_transform_feedback(
+    tf: &mut Self::TransformFeedbackRepr,
+    mode: TransformFeedbackMode,
+    discard_rasterizer: bool,
+  );
+
+  unsafe fn end_transform_feedback(tf: &mut Self::TransformFeedbackRepr);
+
+  /// Number of primitives captured by the last completed session, as reported by
+  /// `GL_TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN`.
+  unsafe fn primitives_written(tf: &Self::TransformFeedbackRepr) -> u32;
+}